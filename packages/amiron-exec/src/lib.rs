@@ -1,17 +1,19 @@
 use wasm_bindgen::prelude::*;
-use std::collections::HashMap;
+use roaring::RoaringBitmap;
+use std::collections::{HashMap, HashSet};
 
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct TaskId(u32);
 
 #[wasm_bindgen]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TaskState {
     Ready,
     Running,
     Waiting,
     Terminated,
+    Stashed,
 }
 
 #[wasm_bindgen]
@@ -19,6 +21,21 @@ pub struct Task {
     id: TaskId,
     priority: u8,
     state: TaskState,
+    // Tick this task last won `schedule()`, and the tick it's been sitting
+    // Ready since — aging compares `enqueue_tick` with the current tick to
+    // grow its effective priority.
+    last_scheduled_tick: u32,
+    enqueue_tick: u32,
+    // Tick at which a Waiting task should return to Ready, if any.
+    wait_deadline: Option<u32>,
+    // Whether `wait_deadline` came from `wait_with_backoff` (and so should
+    // recompute and grow on timeout) rather than a plain `wait_until`.
+    is_backoff: bool,
+    backoff_base: u32,
+    backoff_max: u32,
+    backoff_factor: u32,
+    backoff_interval: u32,
+    backoff_attempt: u32,
 }
 
 #[wasm_bindgen]
@@ -27,6 +44,47 @@ pub struct Exec {
     next_id: u32,
     current_task: Option<TaskId>,
     message_queues: HashMap<TaskId, Vec<Vec<u8>>>,
+    // Insertion-order queue of every task created (entries are never removed;
+    // `schedule` only considers the Ready ones), so `switch` has something
+    // stable to reorder and `schedule` has a tie-breaker beyond raw priority.
+    queue: Vec<TaskId>,
+    current_tick: u32,
+    batching_enabled: bool,
+    debounce_ticks: u32,
+    max_batch_size: usize,
+    max_bytes: usize,
+    // Messages waiting to be coalesced, keyed by recipient, with the tick
+    // each one arrived so we can tell how long the oldest has been sitting.
+    pending_messages: HashMap<TaskId, Vec<(u32, Vec<u8>)>>,
+    // Topic -> subscribed tasks, for `publish` fan-out.
+    topics: HashMap<u32, HashSet<TaskId>>,
+    // How many ticks of waiting add one point of effective priority.
+    aging_interval: u32,
+    // One bitmap per TaskState, keyed by each task's numeric id, kept in
+    // sync with `tasks` on every transition so membership/count queries are
+    // O(popcount) instead of a full scan.
+    state_bitmaps: HashMap<TaskState, RoaringBitmap>,
+    // One bitmap per priority level (0..=255), set once at creation since
+    // priority never changes after a task is made.
+    priority_bitmaps: Vec<RoaringBitmap>,
+    paused: bool,
+    // Scheduling round counter, bumped on every unpaused `schedule()`; the
+    // tranquility throttle itself counts per task in `skip_counter`.
+    round: u32,
+    // Task -> n: scheduler skips this task on n out of every n+1 rounds.
+    tranquility: HashMap<TaskId, u32>,
+    // Task -> how many rounds it's been skipped since last dispatched.
+    skip_counter: HashMap<TaskId, u32>,
+}
+
+/// Read-only snapshot of one task for `Exec::worker_report`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct WorkerInfo {
+    pub id: TaskId,
+    pub priority: u8,
+    pub state: TaskState,
+    pub queued_messages: u32,
 }
 
 #[wasm_bindgen]
@@ -38,34 +96,120 @@ impl Exec {
             next_id: 1,
             current_task: None,
             message_queues: HashMap::new(),
+            queue: Vec::new(),
+            current_tick: 0,
+            batching_enabled: false,
+            debounce_ticks: 0,
+            max_batch_size: 0,
+            max_bytes: 0,
+            pending_messages: HashMap::new(),
+            topics: HashMap::new(),
+            aging_interval: u32::MAX,
+            state_bitmaps: HashMap::new(),
+            priority_bitmaps: (0..256).map(|_| RoaringBitmap::new()).collect(),
+            paused: false,
+            round: 0,
+            tranquility: HashMap::new(),
+            skip_counter: HashMap::new(),
         }
     }
-    
+
     pub fn create_task(&mut self, priority: u8) -> TaskId {
         let id = TaskId(self.next_id);
         self.next_id += 1;
-        
+
         let task = Task {
             id,
             priority,
             state: TaskState::Ready,
+            last_scheduled_tick: 0,
+            enqueue_tick: self.current_tick,
+            wait_deadline: None,
+            is_backoff: false,
+            backoff_base: 0,
+            backoff_max: 0,
+            backoff_factor: 1,
+            backoff_interval: 0,
+            backoff_attempt: 0,
         };
-        
+
         self.tasks.insert(id, task);
         self.message_queues.insert(id, Vec::new());
-        
+        self.queue.push(id);
+        self.state_bitmaps
+            .entry(TaskState::Ready)
+            .or_default()
+            .insert(id.0);
+        self.priority_bitmaps[priority as usize].insert(id.0);
+
         id
     }
-    
+
     pub fn send_message(&mut self, to: TaskId, msg: Vec<u8>) -> bool {
-        if let Some(queue) = self.message_queues.get_mut(&to) {
+        if !self.message_queues.contains_key(&to) {
+            return false;
+        }
+
+        if self.batching_enabled {
+            self.pending_messages
+                .entry(to)
+                .or_default()
+                .push((self.current_tick, msg));
+            true
+        } else if let Some(queue) = self.message_queues.get_mut(&to) {
             queue.push(msg);
             true
         } else {
             false
         }
     }
-    
+
+    /// Turns on debounced batching: messages sent while this is active are
+    /// coalesced per-recipient and only handed to `message_queues` once
+    /// `tick()` decides the batch is ready to flush.
+    pub fn enable_batching(&mut self, debounce_ticks: u32, max_batch_size: usize, max_bytes: usize) {
+        self.batching_enabled = true;
+        self.debounce_ticks = debounce_ticks;
+        self.max_batch_size = max_batch_size;
+        self.max_bytes = max_bytes;
+    }
+
+    /// Advances the executor's clock by one tick and flushes any pending
+    /// batch that has become due (debounce elapsed, or the size/byte cap was
+    /// reached). A recipient's task moves from Waiting to Ready on flush.
+    pub fn tick(&mut self) {
+        self.current_tick += 1;
+        self.expire_waits();
+
+        let due: Vec<TaskId> = self
+            .pending_messages
+            .iter()
+            .filter(|(_, pending)| !pending.is_empty())
+            .filter(|(_, pending)| {
+                let oldest_tick = pending[0].0;
+                let total_bytes: usize = pending.iter().map(|(_, m)| m.len()).sum();
+                self.current_tick.saturating_sub(oldest_tick) > self.debounce_ticks
+                    || pending.len() >= self.max_batch_size
+                    || total_bytes >= self.max_bytes
+            })
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in due {
+            let Some(pending) = self.pending_messages.remove(&id) else {
+                continue;
+            };
+            let batch: Vec<Vec<u8>> = pending.into_iter().map(|(_, msg)| msg).collect();
+            if let Some(queue) = self.message_queues.get_mut(&id) {
+                queue.push(encode_batch(&batch));
+            }
+            let is_waiting = self.tasks.get(&id).map(|t| t.state == TaskState::Waiting).unwrap_or(false);
+            if is_waiting {
+                self.set_state(id, TaskState::Ready);
+            }
+        }
+    }
+
     pub fn receive_message(&mut self, task: TaskId) -> Option<Vec<u8>> {
         self.message_queues
             .get_mut(&task)
@@ -77,23 +221,400 @@ impl Exec {
                 }
             })
     }
-    
+
     pub fn schedule(&mut self) -> Option<TaskId> {
+        if self.paused {
+            return None;
+        }
+        self.round += 1;
+
+        let current_tick = self.current_tick;
+        let aging_interval = self.aging_interval;
+
+        let mut candidates: Vec<(u32, std::cmp::Reverse<u32>, usize, TaskId)> = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter_map(|(pos, id)| {
+                self.tasks.get(id).filter(|t| t.state == TaskState::Ready).map(|t| {
+                    let effective = t.priority as u32
+                        + current_tick.saturating_sub(t.enqueue_tick) / aging_interval;
+                    (effective, std::cmp::Reverse(t.enqueue_tick), pos, *id)
+                })
+            })
+            .collect();
+        // Highest effective priority first; (effective, Reverse(enqueue_tick), pos)
+        // is already unique per task, so the first entry is the max_by_key pick
+        // over that tuple: among equal effective priority and enqueue tick, the
+        // later queue position wins.
+        // Key on only the ranking fields — TaskId doesn't implement Ord and
+        // isn't needed to break ties, since `pos` already does that uniquely.
+        candidates.sort_by_key(|&(effective, rev_enqueue, pos, _)| std::cmp::Reverse((effective, rev_enqueue, pos)));
+
+        let winner = candidates
+            .into_iter()
+            .find(|(_, _, _, id)| self.pass_tranquility(*id))
+            .map(|(_, _, _, id)| id);
+
+        if let Some(id) = winner {
+            self.skip_counter.insert(id, 0);
+            if let Some(task) = self.tasks.get_mut(&id) {
+                task.last_scheduled_tick = current_tick;
+                task.enqueue_tick = current_tick;
+            }
+        }
+        winner
+    }
+
+    /// Pauses scheduling: `schedule()` returns `None` until `resume()`.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes scheduling after `pause()`.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Sets `id`'s tranquility throttle: the scheduler skips it on `n` out of
+    /// every `n + 1` scheduling rounds it would otherwise win.
+    pub fn set_tranquility(&mut self, id: TaskId, n: u32) {
+        self.tranquility.insert(id, n);
+        self.skip_counter.insert(id, 0);
+    }
+
+    /// Moves `id` to Waiting with a plain deadline: `tick()` returns it to
+    /// Ready once `current_tick` reaches `deadline_tick`.
+    pub fn wait_until(&mut self, id: TaskId, deadline_tick: u32) -> bool {
+        if !self.tasks.contains_key(&id) {
+            return false;
+        }
+        self.set_state(id, TaskState::Waiting);
+        let task = self.tasks.get_mut(&id).unwrap();
+        task.is_backoff = false;
+        task.wait_deadline = Some(deadline_tick);
+        true
+    }
+
+    /// Moves `id` to Waiting with capped exponential backoff, starting a
+    /// fresh sequence at `base_ticks`. When the deadline passes, `tick()`
+    /// grows the stored interval by `factor` (capped at `max_ticks`) and
+    /// returns the task to Ready — call `rearm_backoff` to wait again using
+    /// that grown interval instead of restarting from `base_ticks`.
+    pub fn wait_with_backoff(&mut self, id: TaskId, base_ticks: u32, max_ticks: u32, factor: u32) -> bool {
+        if !self.tasks.contains_key(&id) {
+            return false;
+        }
+        self.set_state(id, TaskState::Waiting);
+        let current_tick = self.current_tick;
+        let task = self.tasks.get_mut(&id).unwrap();
+        task.is_backoff = true;
+        task.backoff_base = base_ticks;
+        task.backoff_max = max_ticks;
+        task.backoff_factor = factor;
+        task.backoff_interval = base_ticks;
+        task.backoff_attempt = 0;
+        task.wait_deadline = Some(current_tick + base_ticks);
+        true
+    }
+
+    /// Re-enters Waiting using the backoff interval already grown by a
+    /// prior timeout, instead of restarting from `base_ticks`. Returns
+    /// `false` if `id` doesn't exist or its latest wait was not started by
+    /// `wait_with_backoff` (a later `wait_until` ends backoff mode).
+    pub fn rearm_backoff(&mut self, id: TaskId) -> bool {
+        let is_backoff = self.tasks.get(&id).map(|t| t.is_backoff).unwrap_or(false);
+        if !is_backoff {
+            return false;
+        }
+        let current_tick = self.current_tick;
+        self.set_state(id, TaskState::Waiting);
+        let task = self.tasks.get_mut(&id).unwrap();
+        task.wait_deadline = Some(current_tick + task.backoff_interval);
+        true
+    }
+
+    /// Resets a task's backoff interval to its base, typically called after
+    /// it successfully receives a message.
+    pub fn clear_backoff(&mut self, id: TaskId) {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.backoff_interval = task.backoff_base;
+            task.backoff_attempt = 0;
+        }
+    }
+
+    /// The current backoff interval for `id`, if its latest wait was started
+    /// by `wait_with_backoff` (a later `wait_until` ends backoff mode).
+    pub fn backoff_interval(&self, id: TaskId) -> Option<u32> {
+        self.tasks.get(&id).filter(|t| t.is_backoff).map(|t| t.backoff_interval)
+    }
+
+    /// How many times `id`'s backoff wait has timed out since it was last
+    /// cleared, if its latest wait was started by `wait_with_backoff`.
+    pub fn backoff_attempt(&self, id: TaskId) -> Option<u32> {
+        self.tasks.get(&id).filter(|t| t.is_backoff).map(|t| t.backoff_attempt)
+    }
+
+    /// Snapshot of every task's id, priority, state, and queued message count.
+    pub fn worker_report(&self) -> Vec<WorkerInfo> {
         self.tasks
             .values()
-            .filter(|t| t.state == TaskState::Ready)
-            .max_by_key(|t| t.priority)
-            .map(|t| t.id)
+            .map(|t| WorkerInfo {
+                id: t.id,
+                priority: t.priority,
+                state: t.state,
+                queued_messages: self.message_queues.get(&t.id).map(|q| q.len() as u32).unwrap_or(0),
+            })
+            .collect()
     }
-    
-    pub fn terminate_task(&mut self, id: TaskId) {
-        if let Some(task) = self.tasks.get_mut(&id) {
-            task.state = TaskState::Terminated;
+
+    /// Sets how many ticks of waiting are worth one point of effective
+    /// priority in `schedule`'s aging calculation.
+    pub fn set_aging_interval(&mut self, ticks: u32) {
+        self.aging_interval = ticks.max(1);
+    }
+
+    /// Moves a Ready task out of scheduling contention without terminating it.
+    /// Stashed tasks keep their place in `queue` so `enqueue` restores it.
+    pub fn stash(&mut self, id: TaskId) -> bool {
+        let can = self.tasks.get(&id).is_some_and(|t| t.state == TaskState::Ready);
+        if can {
+            self.set_state(id, TaskState::Stashed);
+        }
+        can
+    }
+
+    /// Moves a Stashed task back into the Ready pool.
+    pub fn enqueue(&mut self, id: TaskId) -> bool {
+        let can = self.tasks.get(&id).is_some_and(|t| t.state == TaskState::Stashed);
+        if can {
+            self.set_state(id, TaskState::Ready);
+            self.tasks.get_mut(&id).unwrap().enqueue_tick = self.current_tick;
+        }
+        can
+    }
+
+    /// Swaps the queue positions of two Ready/Stashed tasks, changing which
+    /// one `schedule` prefers when priorities tie.
+    pub fn switch(&mut self, a: TaskId, b: TaskId) -> bool {
+        let schedulable = |task: &Task| {
+            matches!(task.state, TaskState::Ready | TaskState::Stashed)
+        };
+        let a_ok = self.tasks.get(&a).is_some_and(schedulable);
+        let b_ok = self.tasks.get(&b).is_some_and(schedulable);
+        if !a_ok || !b_ok {
+            return false;
+        }
+
+        let a_idx = self.queue.iter().position(|id| *id == a);
+        let b_idx = self.queue.iter().position(|id| *id == b);
+        match (a_idx, b_idx) {
+            (Some(i), Some(j)) => {
+                self.queue.swap(i, j);
+                true
+            }
+            _ => false,
         }
+    }
+
+    /// Brings a Terminated task back to Ready, clearing whatever was left in
+    /// its mailbox.
+    pub fn restart(&mut self, id: TaskId) -> bool {
+        let can = self.tasks.get(&id).is_some_and(|t| t.state == TaskState::Terminated);
+        if can {
+            self.set_state(id, TaskState::Ready);
+            self.tasks.get_mut(&id).unwrap().enqueue_tick = self.current_tick;
+            self.message_queues.insert(id, Vec::new());
+            if !self.queue.contains(&id) {
+                self.queue.push(id);
+            }
+        }
+        can
+    }
+
+    pub fn terminate_task(&mut self, id: TaskId) {
+        self.set_state(id, TaskState::Terminated);
         self.message_queues.remove(&id);
+        self.pending_messages.remove(&id);
+        for subscribers in self.topics.values_mut() {
+            subscribers.remove(&id);
+        }
+    }
+
+    /// Subscribes `task` to `topic`; future `publish` calls on that topic
+    /// will deliver to it.
+    pub fn subscribe(&mut self, task: TaskId, topic: u32) {
+        self.topics.entry(topic).or_default().insert(task);
+    }
+
+    /// Removes `task` from `topic`'s subscriber set.
+    pub fn unsubscribe(&mut self, task: TaskId, topic: u32) {
+        if let Some(subscribers) = self.topics.get_mut(&topic) {
+            subscribers.remove(&task);
+        }
+    }
+
+    /// Delivers a clone of `msg` to every non-terminated subscriber of
+    /// `topic`, waking any that were Waiting, and returns how many received it.
+    pub fn publish(&mut self, topic: u32, msg: Vec<u8>) -> u32 {
+        let Some(subscribers) = self.topics.get(&topic) else {
+            return 0;
+        };
+        let subscribers: Vec<TaskId> = subscribers.iter().copied().collect();
+
+        let mut delivered = 0;
+        for id in subscribers {
+            let is_terminated = self
+                .tasks
+                .get(&id)
+                .map(|t| t.state == TaskState::Terminated)
+                .unwrap_or(true);
+            if is_terminated {
+                continue;
+            }
+
+            if let Some(queue) = self.message_queues.get_mut(&id) {
+                queue.push(msg.clone());
+                delivered += 1;
+            }
+
+            let is_waiting = self.tasks.get(&id).map(|t| t.state == TaskState::Waiting).unwrap_or(false);
+            if is_waiting {
+                self.set_state(id, TaskState::Ready);
+            }
+        }
+        delivered
+    }
+
+    /// Number of tasks currently in `state`.
+    pub fn count_in_state(&self, state: TaskState) -> u32 {
+        self.state_bitmaps.get(&state).map(|b| b.len() as u32).unwrap_or(0)
+    }
+
+    /// All tasks currently in `state`.
+    pub fn tasks_in_state(&self, state: TaskState) -> Vec<TaskId> {
+        self.state_bitmaps
+            .get(&state)
+            .map(|b| b.iter().map(TaskId).collect())
+            .unwrap_or_default()
+    }
+
+    /// All Ready tasks whose priority is at or above `priority`.
+    pub fn ready_at_or_above(&self, priority: u8) -> Vec<TaskId> {
+        let mut union = RoaringBitmap::new();
+        for bucket in &self.priority_bitmaps[priority as usize..] {
+            union |= bucket;
+        }
+        match self.state_bitmaps.get(&TaskState::Ready) {
+            Some(ready) => (union & ready).iter().map(TaskId).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl Exec {
+    /// Pops the next flushed batch for `task`, decoding it back into the
+    /// individual messages it was built from. Not exposed to wasm-bindgen:
+    /// a nested `Vec<Vec<u8>>` has no ABI, so JS hosts read the raw blob via
+    /// `receive_message` and decode it themselves.
+    pub fn receive_batch(&mut self, task: TaskId) -> Option<Vec<Vec<u8>>> {
+        self.receive_message(task).map(|blob| decode_batch(&blob))
+    }
+
+    /// Moves `id` from whatever state it's currently in to `new_state`,
+    /// keeping `state_bitmaps` in lockstep with `tasks` so the two never
+    /// disagree.
+    fn set_state(&mut self, id: TaskId, new_state: TaskState) {
+        let Some(task) = self.tasks.get_mut(&id) else {
+            return;
+        };
+        let old_state = task.state;
+        if old_state == new_state {
+            return;
+        }
+        task.state = new_state;
+
+        if let Some(bitmap) = self.state_bitmaps.get_mut(&old_state) {
+            bitmap.remove(id.0);
+        }
+        self.state_bitmaps
+            .entry(new_state)
+            .or_default()
+            .insert(id.0);
+    }
+
+    /// Applies `id`'s tranquility throttle: returns `false` (and bumps its
+    /// skip count) `n` times in a row, then `true` on the `n + 1`th round.
+    fn pass_tranquility(&mut self, id: TaskId) -> bool {
+        let n = self.tranquility.get(&id).copied().unwrap_or(0);
+        if n == 0 {
+            return true;
+        }
+        let skipped = self.skip_counter.entry(id).or_insert(0);
+        if *skipped < n {
+            *skipped += 1;
+            false
+        } else {
+            *skipped = 0;
+            true
+        }
+    }
+
+    /// Wakes every Waiting task whose deadline has passed, clearing the
+    /// deadline; for backoff waits it also grows the stored interval (capped)
+    /// and bumps the attempt count. It does not re-enter Waiting itself.
+    fn expire_waits(&mut self) {
+        let current_tick = self.current_tick;
+        let timed_out: Vec<TaskId> = self
+            .tasks
+            .values()
+            .filter(|t| t.state == TaskState::Waiting)
+            .filter(|t| t.wait_deadline.is_some_and(|deadline| current_tick >= deadline))
+            .map(|t| t.id)
+            .collect();
+
+        for id in timed_out {
+            if let Some(task) = self.tasks.get_mut(&id) {
+                if task.is_backoff {
+                    task.backoff_attempt += 1;
+                    task.backoff_interval =
+                        (task.backoff_interval.saturating_mul(task.backoff_factor.max(1)))
+                            .min(task.backoff_max);
+                }
+                task.wait_deadline = None;
+            }
+            self.set_state(id, TaskState::Ready);
+        }
     }
 }
 
+/// Concatenates a batch of messages into one length-prefixed blob so it can
+/// travel through `message_queues` as a single entry.
+fn encode_batch(batch: &[Vec<u8>]) -> Vec<u8> {
+    let mut blob = Vec::new();
+    for msg in batch {
+        blob.extend_from_slice(&(msg.len() as u32).to_le_bytes());
+        blob.extend_from_slice(msg);
+    }
+    blob
+}
+
+/// Inverse of `encode_batch`.
+fn decode_batch(blob: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+    while offset + 4 <= blob.len() {
+        let len = u32::from_le_bytes(blob[offset..offset + 4].try_into().unwrap()) as usize;
+        offset += 4;
+        if offset + len > blob.len() {
+            break;
+        }
+        messages.push(blob[offset..offset + len].to_vec());
+        offset += len;
+    }
+    messages
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,4 +625,357 @@ mod tests {
         let task_id = exec.create_task(5);
         assert!(exec.tasks.contains_key(&task_id));
     }
+
+    #[test]
+    fn test_stash_skipped_by_schedule() {
+        let mut exec = Exec::new();
+        let low = exec.create_task(1);
+        let high = exec.create_task(5);
+        assert!(exec.stash(high));
+        assert_eq!(exec.schedule(), Some(low));
+    }
+
+    #[test]
+    fn test_enqueue_restores_to_ready() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        assert!(exec.stash(task));
+        assert!(exec.enqueue(task));
+        assert_eq!(exec.schedule(), Some(task));
+    }
+
+    #[test]
+    fn test_enqueue_resets_stale_enqueue_tick() {
+        let mut exec = Exec::new();
+        let stashed = exec.create_task(1);
+        assert!(exec.stash(stashed));
+        exec.set_aging_interval(1);
+        for _ in 0..50 {
+            exec.tick();
+        }
+        let fresh = exec.create_task(1);
+
+        assert!(exec.enqueue(stashed));
+        assert_eq!(exec.schedule(), Some(fresh));
+    }
+
+    #[test]
+    fn test_switch_breaks_priority_ties() {
+        let mut exec = Exec::new();
+        let first = exec.create_task(3);
+        let second = exec.create_task(3);
+        assert_eq!(exec.schedule(), Some(second));
+        assert!(exec.switch(first, second));
+        assert_eq!(exec.schedule(), Some(first));
+    }
+
+    #[test]
+    fn test_restart_clears_message_queue() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.send_message(task, vec![1, 2, 3]);
+        exec.terminate_task(task);
+        assert!(exec.restart(task));
+        assert_eq!(exec.receive_message(task), None);
+    }
+
+    #[test]
+    fn test_restart_resets_stale_enqueue_tick() {
+        let mut exec = Exec::new();
+        let terminated = exec.create_task(1);
+        exec.terminate_task(terminated);
+        exec.set_aging_interval(1);
+        for _ in 0..50 {
+            exec.tick();
+        }
+        let fresh = exec.create_task(1);
+
+        assert!(exec.restart(terminated));
+        assert_eq!(exec.schedule(), Some(fresh));
+    }
+
+    #[test]
+    fn test_batching_flushes_on_debounce() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.enable_batching(2, 100, 1000);
+
+        exec.send_message(task, vec![1]);
+        exec.send_message(task, vec![2, 2]);
+        assert_eq!(exec.receive_batch(task), None);
+
+        exec.tick();
+        exec.tick();
+        exec.tick();
+
+        assert_eq!(exec.receive_batch(task), Some(vec![vec![1], vec![2, 2]]));
+    }
+
+    #[test]
+    fn test_batching_flushes_on_max_batch_size() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.enable_batching(1000, 2, 1000);
+
+        exec.send_message(task, vec![9]);
+        exec.send_message(task, vec![8]);
+        exec.tick();
+
+        assert_eq!(exec.receive_batch(task), Some(vec![vec![9], vec![8]]));
+    }
+
+    #[test]
+    fn test_batching_flushes_oversized_single_message() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.enable_batching(1000, 100, 4);
+
+        exec.send_message(task, vec![0; 10]);
+        exec.tick();
+
+        assert_eq!(exec.receive_batch(task), Some(vec![vec![0; 10]]));
+    }
+
+    #[test]
+    fn test_batch_flush_wakes_waiting_task() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.tasks.get_mut(&task).unwrap().state = TaskState::Waiting;
+        exec.enable_batching(1, 100, 1000);
+
+        exec.send_message(task, vec![1]);
+        exec.tick();
+        exec.tick();
+
+        assert_eq!(exec.tasks.get(&task).unwrap().state, TaskState::Ready);
+    }
+
+    #[test]
+    fn test_publish_delivers_to_subscribers() {
+        let mut exec = Exec::new();
+        let a = exec.create_task(1);
+        let b = exec.create_task(1);
+        exec.subscribe(a, 42);
+        exec.subscribe(b, 42);
+
+        let delivered = exec.publish(42, vec![7]);
+
+        assert_eq!(delivered, 2);
+        assert_eq!(exec.receive_message(a), Some(vec![7]));
+        assert_eq!(exec.receive_message(b), Some(vec![7]));
+    }
+
+    #[test]
+    fn test_unsubscribe_stops_delivery() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.subscribe(task, 1);
+        exec.unsubscribe(task, 1);
+
+        assert_eq!(exec.publish(1, vec![1]), 0);
+    }
+
+    #[test]
+    fn test_terminate_prunes_subscription() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.subscribe(task, 1);
+        exec.terminate_task(task);
+
+        assert_eq!(exec.publish(1, vec![1]), 0);
+    }
+
+    #[test]
+    fn test_publish_wakes_waiting_subscriber() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.tasks.get_mut(&task).unwrap().state = TaskState::Waiting;
+        exec.subscribe(task, 9);
+
+        exec.publish(9, vec![1]);
+
+        assert_eq!(exec.tasks.get(&task).unwrap().state, TaskState::Ready);
+    }
+
+    #[test]
+    fn test_aging_lets_stale_low_priority_task_win() {
+        let mut exec = Exec::new();
+        let low = exec.create_task(1);
+        exec.set_aging_interval(1);
+
+        for _ in 0..10 {
+            exec.tick();
+        }
+        let high = exec.create_task(5);
+
+        assert_eq!(exec.schedule(), Some(low));
+        let _ = high;
+    }
+
+    #[test]
+    fn test_schedule_resets_enqueue_tick_on_dispatch() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.set_aging_interval(1);
+        for _ in 0..5 {
+            exec.tick();
+        }
+
+        assert_eq!(exec.schedule(), Some(task));
+        assert_eq!(exec.tasks.get(&task).unwrap().enqueue_tick, 5);
+    }
+
+    #[test]
+    fn test_no_aging_by_default() {
+        let mut exec = Exec::new();
+        let low = exec.create_task(1);
+        for _ in 0..1000 {
+            exec.tick();
+        }
+        let high = exec.create_task(5);
+
+        assert_eq!(exec.schedule(), Some(high));
+        let _ = low;
+    }
+
+    #[test]
+    fn test_count_in_state_tracks_transitions() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        assert_eq!(exec.count_in_state(TaskState::Ready), 1);
+        assert_eq!(exec.count_in_state(TaskState::Stashed), 0);
+
+        exec.stash(task);
+        assert_eq!(exec.count_in_state(TaskState::Ready), 0);
+        assert_eq!(exec.count_in_state(TaskState::Stashed), 1);
+    }
+
+    #[test]
+    fn test_tasks_in_state_lists_members() {
+        let mut exec = Exec::new();
+        let a = exec.create_task(1);
+        let b = exec.create_task(2);
+        exec.stash(b);
+
+        let mut ready: Vec<TaskId> = exec.tasks_in_state(TaskState::Ready);
+        ready.sort_by_key(|t| t.0);
+        assert_eq!(ready, vec![a]);
+    }
+
+    #[test]
+    fn test_ready_at_or_above_filters_by_priority_and_state() {
+        let mut exec = Exec::new();
+        let low = exec.create_task(1);
+        let high = exec.create_task(9);
+        exec.stash(high);
+
+        assert_eq!(exec.ready_at_or_above(0), vec![low]);
+        assert_eq!(exec.ready_at_or_above(9), Vec::<TaskId>::new());
+
+        exec.enqueue(high);
+        let mut at_least_five = exec.ready_at_or_above(5);
+        at_least_five.sort_by_key(|t| t.0);
+        assert_eq!(at_least_five, vec![high]);
+    }
+
+    #[test]
+    fn test_terminate_updates_state_bitmap() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.terminate_task(task);
+
+        assert_eq!(exec.count_in_state(TaskState::Ready), 0);
+        assert_eq!(exec.count_in_state(TaskState::Terminated), 1);
+    }
+
+    #[test]
+    fn test_pause_blocks_scheduling() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.pause();
+        assert_eq!(exec.schedule(), None);
+        exec.resume();
+        assert_eq!(exec.schedule(), Some(task));
+    }
+
+    #[test]
+    fn test_worker_report_reflects_state_and_queue() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(7);
+        exec.send_message(task, vec![1]);
+        exec.send_message(task, vec![2]);
+
+        let report = exec.worker_report();
+        let entry = report.iter().find(|w| w.id == task).unwrap();
+        assert_eq!(entry.priority, 7);
+        assert_eq!(entry.state, TaskState::Ready);
+        assert_eq!(entry.queued_messages, 2);
+    }
+
+    #[test]
+    fn test_tranquility_throttles_dispatch() {
+        let mut exec = Exec::new();
+        let loud = exec.create_task(5);
+        let quiet = exec.create_task(1);
+        exec.set_tranquility(loud, 2);
+
+        assert_eq!(exec.schedule(), Some(quiet));
+        assert_eq!(exec.schedule(), Some(quiet));
+        assert_eq!(exec.schedule(), Some(loud));
+    }
+
+    #[test]
+    fn test_wait_until_times_out_to_ready() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.wait_until(task, 3);
+
+        exec.tick();
+        exec.tick();
+        assert_eq!(exec.tasks.get(&task).unwrap().state, TaskState::Waiting);
+
+        exec.tick();
+        assert_eq!(exec.tasks.get(&task).unwrap().state, TaskState::Ready);
+    }
+
+    #[test]
+    fn test_backoff_grows_interval_and_caps_it() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.wait_with_backoff(task, 2, 10, 3);
+
+        exec.tick();
+        exec.tick();
+        assert_eq!(exec.tasks.get(&task).unwrap().state, TaskState::Ready);
+        assert_eq!(exec.backoff_interval(task), Some(6));
+        assert_eq!(exec.backoff_attempt(task), Some(1));
+
+        assert!(exec.rearm_backoff(task));
+        for _ in 0..6 {
+            exec.tick();
+        }
+        assert_eq!(exec.backoff_interval(task), Some(10));
+        assert_eq!(exec.backoff_attempt(task), Some(2));
+    }
+
+    #[test]
+    fn test_clear_backoff_resets_interval() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        exec.wait_with_backoff(task, 2, 10, 3);
+        exec.tick();
+        exec.tick();
+        assert_eq!(exec.backoff_interval(task), Some(6));
+
+        exec.clear_backoff(task);
+        assert_eq!(exec.backoff_interval(task), Some(2));
+    }
+
+    #[test]
+    fn test_rearm_backoff_requires_prior_backoff_wait() {
+        let mut exec = Exec::new();
+        let task = exec.create_task(1);
+        assert!(!exec.rearm_backoff(task));
+        assert_eq!(exec.backoff_interval(task), None);
+    }
 }